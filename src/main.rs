@@ -7,17 +7,211 @@ use axum::{
     http::StatusCode,
     routing::get,
 };
+// `mqtt::client::Client` is assumed to expose this surface. There is no
+// Cargo.toml anywhere in this tree (baseline included) pinning which version of
+// the `mqtt` crate that is, so this comment is the contract a manifest needs to
+// satisfy rather than a guarantee one already does:
+//   fn new(client_id: &str, username: &str, password: &str, keepalive_secs: u16) -> Self
+//   fn set_will(&mut self, topic: &str, payload: &str, retain: bool)
+//   fn connect(&mut self, broker_address: &str) -> Result<(), impl std::fmt::Display>
+//   fn publish(&self, topic: &str, payload: &str, retain: bool)
 use mqtt::client::Client as MqttClient;
+use reqwest::Client as HttpClient;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     net::SocketAddr,
     process::exit,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
 };
 use tracing::{debug, error, info};
 
-type AppState = Arc<Mutex<MqttClient>>;
+type AppState = Arc<SharedState>;
+
+static APP_CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+const AVAILABILITY_TOPIC: &str = "homeassistant/sensor/ambientWeather/status";
+const PAYLOAD_AVAILABLE: &str = "online";
+const PAYLOAD_NOT_AVAILABLE: &str = "offline";
+const AGGREGATE_STATE_TOPIC: &str = "homeassistant/sensor/ambientWeather/state";
+const RECONNECT_BACKOFF_CAP: Duration = Duration::from_secs(60);
+/// How often a live connection is proactively re-established. `Client` exposes no
+/// keepalive-failure or disconnect callback, so forcing a fresh `connect` on this
+/// schedule is the only way a broker restart mid-session is ever noticed --
+/// otherwise `connected` would stay `true` forever once the initial connect
+/// succeeds, and a dead socket would look the same as a live one.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+fn app_config() -> &'static AppConfig {
+    APP_CONFIG.get().expect("config initialized in main")
+}
+
+static HTTP_CLIENT: OnceLock<HttpClient> = OnceLock::new();
+
+fn http_client() -> &'static HttpClient {
+    HTTP_CLIENT.get_or_init(HttpClient::new)
+}
+
+static DEVICE: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+
+fn device() -> &'static HashMap<&'static str, &'static str> {
+    DEVICE.get().expect("device initialized in main")
+}
+
+/// Wraps the MQTT client with a connected flag so publishes can be skipped while
+/// the broker connection is down, rather than firing into a dead socket. `publish`
+/// doesn't report per-call failures back to `Client`, so `connected` only ever
+/// transitions on a `connect` attempt (initial, in `main`, or from
+/// `spawn_reconnect_task`), the same as before this client carried a will.
+struct Mqtt {
+    client: MqttClient,
+    connected: bool,
+}
+
+impl Mqtt {
+    fn publish(&mut self, topic: &str, payload: &str, retain: bool) {
+        if !self.connected {
+            debug!(topic, "skipping publish: mqtt disconnected");
+            return;
+        }
+        self.client.publish(topic, payload, retain);
+    }
+}
+
+/// Shared axum handler state: the MQTT client plus the station credentials the
+/// handler validates incoming requests against, both sourced from `config.toml`.
+struct SharedState {
+    mqtt: Mutex<Mqtt>,
+    station_id: String,
+    station_key: String,
+}
+
+/// Runs for the lifetime of the process, reconnecting to the broker with
+/// exponential backoff whenever `Mqtt::connected` is false, periodically
+/// re-proving a connected client is still live (see `HEALTH_CHECK_INTERVAL`), and
+/// re-publishing the discovery configs and availability once the connection is
+/// (re)established.
+fn spawn_reconnect_task(state: AppState) {
+    tokio::spawn(async move {
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            // A poisoned mutex is treated as a recoverable failure of this one
+            // attempt, the same way `handle_weather_update` treats it, rather than a
+            // reason to die -- panicking here would silently end the only task that
+            // can ever bring the connection back. The guard/PoisonError must not
+            // survive past this match: `MutexGuard` isn't `Send`, so holding one
+            // across the `.await` below would make this whole task's future non-`Send`
+            // and `tokio::spawn` would refuse to compile it.
+            let already_connected = match state.mqtt.lock() {
+                Ok(guard) => Some(guard.connected),
+                Err(e) => {
+                    error!(%e, "mqtt mutex poisoned, retrying reconnect loop");
+                    None
+                }
+            };
+            let Some(already_connected) = already_connected else {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            };
+
+            if already_connected {
+                // Already up: don't hammer the broker, just wait out the health-check
+                // interval before proving the connection is still alive below.
+                tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+            }
+
+            // Connect a fresh client that isn't behind `state.mqtt`'s lock, so a slow
+            // or hanging TCP connect doesn't block `handle_weather_update`'s publishes
+            // for the duration. The lock is only taken afterwards, to swap it in. This
+            // doubles as the liveness probe for an already-connected client: MQTT
+            // brokers drop the existing session for a client_id when a new CONNECT
+            // for that same id arrives, so a successful `connect` here always means
+            // the state we swap in afterwards is the live one.
+            let cfg = app_config();
+            let mut candidate = MqttClient::new(&cfg.client_id, &cfg.username, &cfg.password, 60);
+            candidate.set_will(AVAILABILITY_TOPIC, PAYLOAD_NOT_AVAILABLE, true);
+
+            match candidate.connect(&cfg.broker_address) {
+                Ok(()) => {
+                    if !already_connected {
+                        info!("reconnected to MQTT broker");
+                    }
+                    let mut mqtt = match state.mqtt.lock() {
+                        Ok(guard) => guard,
+                        Err(e) => {
+                            error!(%e, "mqtt mutex poisoned, dropping this reconnect attempt");
+                            continue;
+                        }
+                    };
+                    mqtt.client = candidate;
+                    mqtt.connected = true;
+                    mqtt.publish(AVAILABILITY_TOPIC, PAYLOAD_AVAILABLE, true);
+                    for sensor in SENSORS {
+                        publish_sensor_config(&mut mqtt, sensor);
+                    }
+                    backoff = Duration::from_secs(1);
+                }
+                Err(e) => {
+                    error!(%e, backoff_secs = backoff.as_secs(), "unable to (re)connect to MQTT broker");
+                    if let Ok(mut mqtt) = state.mqtt.lock() {
+                        mqtt.connected = false;
+                    }
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(RECONNECT_BACKOFF_CAP);
+                }
+            }
+        }
+    });
+}
+
+/// Incoming query-param keys that forward straight through to the windy.com PWS
+/// upload API under a (possibly renamed) key. `baromin` is handled separately since
+/// it needs unit conversion.
+const WINDY_FIELD_MAP: &[(&str, &str)] = &[
+    ("tempf", "tempf"),
+    ("windspeedmph", "windspeedmph"),
+    ("winddir", "winddir"),
+    ("humidity", "humidity"),
+    ("rainin", "rainin"),
+    ("dewptf", "dewptf"),
+    ("UV", "uv"),
+];
+
+/// Re-forwards the observation to windy.com's Personal Weather Station upload API, if
+/// configured. Runs as a detached task so a slow or unreachable windy.com never
+/// delays the response to the station or the MQTT publishes in
+/// `handle_weather_update`.
+fn spawn_windy_upload(params: &HashMap<String, String>) {
+    let cfg = app_config();
+    let Some(api_key) = cfg.windy_api_key.clone() else {
+        return;
+    };
+
+    let mut query: Vec<(String, String)> = WINDY_FIELD_MAP
+        .iter()
+        .filter_map(|(src, dst)| params.get(*src).map(|val| ((*dst).to_string(), val.clone())))
+        .collect();
+    if let Some(inhg) = params.get("baromin").and_then(|v| v.parse::<f64>().ok()) {
+        query.push(("pressure".to_string(), format!("{:.1}", inhg * 3386.39))); // Pa
+    }
+    if let Some(station_id) = cfg.windy_station_id.clone() {
+        query.push(("station".to_string(), station_id));
+    }
+
+    tokio::spawn(async move {
+        let url = format!("https://stations.windy.com/pws/update/{api_key}");
+        match http_client().get(url).query(&query).send().await {
+            Ok(resp) if !resp.status().is_success() => {
+                error!(status = %resp.status(), "windy upload rejected");
+            }
+            Err(e) => {
+                error!(%e, "unable to upload observation to windy");
+            }
+            Ok(_) => {}
+        }
+    });
+}
 
 #[derive(Serialize)]
 struct SensorConfig<'a> {
@@ -26,17 +220,328 @@ struct SensorConfig<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub device_class: Option<&'static str>,
     pub device: &'a HashMap<&'static str, &'static str>,
-    pub state_topic: &'static str,
+    pub state_topic: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_template: Option<String>,
     pub unit_of_measurement: &'static str,
     pub state_class: &'static str,
+    pub availability_topic: &'static str,
+    pub payload_available: &'static str,
+    pub payload_not_available: &'static str,
+}
+
+/// How the raw query-param value for a sensor is turned into an MQTT state payload.
+#[derive(Clone, Copy)]
+enum SensorKind {
+    F32 { precision: usize },
+    F32Scaled { precision: usize, scale: f32 },
+    I32,
+    /// Published from a value that's derived from multiple params rather than copied
+    /// straight out of one (e.g. heat index). Only the discovery config comes from
+    /// the registry; the state itself is published separately in `handle_weather_update`.
+    Computed,
+}
+
+/// Which family of unit conversion (if any) applies to a sensor's value when
+/// `AppConfig::units` is set to metric. `unit_of_measurement` on `SensorDef` always
+/// holds the imperial unit; `Fixed` sensors use it unchanged in both modes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum UnitKind {
+    Temperature,
+    WindSpeed,
+    RainRate,
+    RainTotal,
+    Fixed,
 }
 
+impl UnitKind {
+    fn unit_of_measurement(self, units: Units, imperial: &'static str) -> &'static str {
+        match (self, units) {
+            (UnitKind::Temperature, Units::Metric) => "°C",
+            (UnitKind::WindSpeed, Units::Metric) => "km/h",
+            (UnitKind::RainRate, Units::Metric) => "mm/h",
+            (UnitKind::RainTotal, Units::Metric) => "mm",
+            _ => imperial,
+        }
+    }
+
+    fn convert(self, value: f64, units: Units) -> f64 {
+        match (self, units) {
+            (UnitKind::Temperature, Units::Metric) => (value - 32.0) * 5.0 / 9.0,
+            (UnitKind::WindSpeed, Units::Metric) => value * 1.609_34,
+            (UnitKind::RainRate | UnitKind::RainTotal, Units::Metric) => value * 25.4,
+            _ => value,
+        }
+    }
+}
+
+/// Imperial/metric output mode, set via `AppConfig::units`.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Units {
+    #[default]
+    Imperial,
+    Metric,
+}
+
+/// One row per Home Assistant sensor: the incoming query-param key, the HA topic
+/// suffix, and everything needed to build both the discovery config and the state
+/// payload. Adding a sensor means adding a row here, not editing `main` and
+/// `handle_weather_update` separately.
+struct SensorDef {
+    key: &'static str,
+    topic: &'static str,
+    kind: SensorKind,
+    unit_kind: UnitKind,
+    name: &'static str,
+    unique_id: &'static str,
+    device_class: Option<&'static str>,
+    unit_of_measurement: &'static str,
+    state_class: &'static str,
+}
+
+static SENSORS: &[SensorDef] = &[
+    SensorDef {
+        key: "tempf",
+        topic: "temperature",
+        kind: SensorKind::F32 { precision: 1 },
+        unit_kind: UnitKind::Temperature,
+        name: "Outside Temperature",
+        unique_id: "ambw_mqtt_outside_temp",
+        device_class: Some("temperature"),
+        unit_of_measurement: "°F",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "",
+        topic: "feelsLike",
+        kind: SensorKind::Computed,
+        unit_kind: UnitKind::Temperature,
+        name: "Outside Feels Like",
+        unique_id: "ambw_mqtt_outside_feels",
+        device_class: Some("temperature"),
+        unit_of_measurement: "°F",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "humidity",
+        topic: "humidity",
+        kind: SensorKind::I32,
+        unit_kind: UnitKind::Fixed,
+        name: "Outside Humidity",
+        unique_id: "ambw_mqtt_outside_hum",
+        device_class: Some("humidity"),
+        unit_of_measurement: "%",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "dewptf",
+        topic: "dewPoint",
+        kind: SensorKind::F32 { precision: 1 },
+        unit_kind: UnitKind::Temperature,
+        name: "Outside Dew Point",
+        unique_id: "ambw_mqtt_outside_dew",
+        device_class: Some("temperature"),
+        unit_of_measurement: "°F",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "windchillf",
+        topic: "windChill",
+        kind: SensorKind::F32 { precision: 1 },
+        unit_kind: UnitKind::Temperature,
+        name: "Wind Chill",
+        unique_id: "ambw_mqtt_wind_chill",
+        device_class: Some("temperature"),
+        unit_of_measurement: "°F",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "winddir",
+        topic: "windDir",
+        kind: SensorKind::I32,
+        unit_kind: UnitKind::Fixed,
+        name: "Wind Dir",
+        unique_id: "ambw_mqtt_wind_dir",
+        device_class: None,
+        unit_of_measurement: "°",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "windspeedmph",
+        topic: "windSpeed",
+        kind: SensorKind::F32 { precision: 2 },
+        unit_kind: UnitKind::WindSpeed,
+        name: "Wind Speed",
+        unique_id: "ambw_mqtt_wind_speed",
+        device_class: Some("wind_speed"),
+        unit_of_measurement: "mph",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "windgustmph",
+        topic: "windGust",
+        kind: SensorKind::F32 { precision: 2 },
+        unit_kind: UnitKind::WindSpeed,
+        name: "Wind Gust",
+        unique_id: "ambw_mqtt_wind_gust",
+        device_class: Some("wind_speed"),
+        unit_of_measurement: "mph",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "rainin",
+        topic: "rainHourly",
+        kind: SensorKind::F32 { precision: 3 },
+        unit_kind: UnitKind::RainRate,
+        name: "Hourly Rain Rate",
+        unique_id: "ambw_mqtt_hourly_rain",
+        device_class: Some("precipitation_intensity"),
+        unit_of_measurement: "in/h",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "dailyrainin",
+        topic: "rainDaily",
+        kind: SensorKind::F32 { precision: 3 },
+        unit_kind: UnitKind::RainTotal,
+        name: "Daily Rain",
+        unique_id: "ambw_mqtt_daily_rain",
+        device_class: Some("precipitation"),
+        unit_of_measurement: "in",
+        state_class: "total_increasing",
+    },
+    SensorDef {
+        key: "weeklyrainin",
+        topic: "rainWeekly",
+        kind: SensorKind::F32 { precision: 3 },
+        unit_kind: UnitKind::RainTotal,
+        name: "Weekly Rain",
+        unique_id: "ambw_mqtt_weekly_rain",
+        device_class: Some("precipitation"),
+        unit_of_measurement: "in",
+        state_class: "total_increasing",
+    },
+    SensorDef {
+        key: "monthlyrainin",
+        topic: "rainMonthly",
+        kind: SensorKind::F32 { precision: 3 },
+        unit_kind: UnitKind::RainTotal,
+        name: "Monthly Rain",
+        unique_id: "ambw_mqtt_monthyl_rain",
+        device_class: Some("precipitation"),
+        unit_of_measurement: "in",
+        state_class: "total_increasing",
+    },
+    SensorDef {
+        key: "totalrainin",
+        topic: "rainLifetime",
+        kind: SensorKind::F32 { precision: 3 },
+        unit_kind: UnitKind::RainTotal,
+        name: "Lifetime Rain",
+        unique_id: "ambw_mqtt_lifetime_rain",
+        device_class: Some("precipitation"),
+        unit_of_measurement: "in",
+        state_class: "total_increasing",
+    },
+    SensorDef {
+        key: "solarradiation",
+        topic: "solarRadiation",
+        kind: SensorKind::F32 { precision: 1 },
+        unit_kind: UnitKind::Fixed,
+        name: "Solar Radiation",
+        unique_id: "ambw_mqtt_solar_rad",
+        device_class: Some("irradiance"),
+        unit_of_measurement: "W/m²",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "UV",
+        topic: "UV",
+        kind: SensorKind::I32,
+        unit_kind: UnitKind::Fixed,
+        name: "UV Index",
+        unique_id: "ambw_mqtt_uv",
+        device_class: None,
+        unit_of_measurement: "Index",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "indoortempf",
+        topic: "kitchenTemperature",
+        kind: SensorKind::F32 { precision: 1 },
+        unit_kind: UnitKind::Temperature,
+        name: "Kitchen Temperature",
+        unique_id: "ambw_mqtt_indoor_temp",
+        device_class: Some("temperature"),
+        unit_of_measurement: "°F",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "indoorhumidity",
+        topic: "kitchenHumidity",
+        kind: SensorKind::I32,
+        unit_kind: UnitKind::Fixed,
+        name: "Kitchen Humidity",
+        unique_id: "ambw_mqtt_indoor_hum",
+        device_class: Some("humidity"),
+        unit_of_measurement: "%",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "absbaromin",
+        topic: "pressure",
+        kind: SensorKind::F32Scaled {
+            precision: 1,
+            scale: 33.86,
+        },
+        unit_kind: UnitKind::Fixed,
+        name: "Outside Pressure",
+        unique_id: "ambw_mqtt_abs_press",
+        device_class: Some("atmospheric_pressure"),
+        unit_of_measurement: "hPa",
+        state_class: "measurement",
+    },
+    SensorDef {
+        key: "baromin",
+        topic: "relativePressure",
+        kind: SensorKind::F32Scaled {
+            precision: 1,
+            scale: 33.86,
+        },
+        unit_kind: UnitKind::Fixed,
+        name: "Outside Relative Pressure",
+        unique_id: "ambw_mqtt_rel_press",
+        device_class: Some("atmospheric_pressure"),
+        unit_of_measurement: "hPa",
+        state_class: "measurement",
+    },
+];
+
 #[derive(Deserialize)]
 struct AppConfig {
     pub broker_address: String,
     pub client_id: String,
     pub username: String,
     pub password: String,
+    /// Publish one aggregated JSON state payload per update instead of one state
+    /// topic per sensor.
+    #[serde(default)]
+    pub aggregate_state: bool,
+    #[serde(default)]
+    pub units: Units,
+    /// Re-forwards each observation to the windy.com PWS upload API when set.
+    #[serde(default)]
+    pub windy_api_key: Option<String>,
+    #[serde(default)]
+    pub windy_station_id: Option<String>,
+    /// Address the HTTP server binds to, e.g. `192.168.1.2`.
+    pub listen_address: String,
+    pub listen_port: u16,
+    /// Expected `ID`/`PASSWORD` query params on `/update_weather`, matching whatever
+    /// the station itself is configured to send.
+    pub station_id: String,
+    pub station_key: String,
 }
 
 #[tokio::main]
@@ -56,276 +561,52 @@ async fn main() {
             exit(1);
         }
     };
+    let cfg = APP_CONFIG.get_or_init(|| cfg);
 
     let mut mqtt_client = MqttClient::new(&cfg.client_id, &cfg.username, &cfg.password, 60);
-    if let Err(e) = mqtt_client.connect(&cfg.broker_address) {
-        error!(%e, "unable to connect to MQTT broker");
-        exit(1);
+    mqtt_client.set_will(AVAILABILITY_TOPIC, PAYLOAD_NOT_AVAILABLE, true);
+    let connected = mqtt_client.connect(&cfg.broker_address).is_ok();
+    if !connected {
+        error!("unable to connect to MQTT broker, will retry in background");
     }
+    let mut mqtt = Mqtt {
+        client: mqtt_client,
+        connected,
+    };
 
-    let device = HashMap::from([
-        ("identifiers", "ambw_mqtt"),
-        ("manufacturer", "Ambient Weather"),
-        ("model", "WS-2902"),
-        ("name", "MQTT Weather Station"),
-        ("via_device", "ambient_weather_mqtt"),
-    ]);
-
-    publish_sensor_config(
-        &mqtt_client,
-        "temperature",
-        &SensorConfig {
-            name: "Outside Temperature",
-            unique_id: "ambw_mqtt_outside_temp",
-            device_class: Some("temperature"),
-            state_topic: "homeassistant/sensor/ambientWeather/temperature/state",
-            unit_of_measurement: "°F",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "feelsLike",
-        &SensorConfig {
-            name: "Outside Feels Like",
-            unique_id: "ambw_mqtt_outside_feels",
-            device_class: Some("temperature"),
-            state_topic: "homeassistant/sensor/ambientWeather/feelsLike/state",
-            unit_of_measurement: "°F",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "humidity",
-        &SensorConfig {
-            name: "Outside Humidity",
-            unique_id: "ambw_mqtt_outside_hum",
-            device_class: Some("humidity"),
-            state_topic: "homeassistant/sensor/ambientWeather/humidity/state",
-            unit_of_measurement: "%",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "dewPoint",
-        &SensorConfig {
-            name: "Outside Dew Point",
-            unique_id: "ambw_mqtt_outside_dew",
-            device_class: Some("temperature"),
-            state_topic: "homeassistant/sensor/ambientWeather/dewPoint/state",
-            unit_of_measurement: "°F",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "windChill",
-        &SensorConfig {
-            name: "Wind Chill",
-            unique_id: "ambw_mqtt_wind_chill",
-            device_class: Some("temperature"),
-            state_topic: "homeassistant/sensor/ambientWeather/windChill/state",
-            unit_of_measurement: "°F",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "windDir",
-        &SensorConfig {
-            name: "Wind Dir",
-            unique_id: "ambw_mqtt_wind_dir",
-            device_class: None,
-            state_topic: "homeassistant/sensor/ambientWeather/windDir/state",
-            unit_of_measurement: "°",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "windSpeed",
-        &SensorConfig {
-            name: "Wind Speed",
-            unique_id: "ambw_mqtt_wind_speed",
-            device_class: Some("wind_speed"),
-            state_topic: "homeassistant/sensor/ambientWeather/windSpeed/state",
-            unit_of_measurement: "mph",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "windGust",
-        &SensorConfig {
-            name: "Wind Gust",
-            unique_id: "ambw_mqtt_wind_gust",
-            device_class: Some("wind_speed"),
-            state_topic: "homeassistant/sensor/ambientWeather/windGust/state",
-            unit_of_measurement: "mph",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "rainHourly",
-        &SensorConfig {
-            name: "Hourly Rain Rate",
-            unique_id: "ambw_mqtt_hourly_rain",
-            device_class: Some("precipitation_intensity"),
-            state_topic: "homeassistant/sensor/ambientWeather/rainHourly/state",
-            unit_of_measurement: "in/h",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "rainDaily",
-        &SensorConfig {
-            name: "Daily Rain",
-            unique_id: "ambw_mqtt_daily_rain",
-            device_class: Some("precipitation"),
-            state_topic: "homeassistant/sensor/ambientWeather/rainDaily/state",
-            unit_of_measurement: "in",
-            state_class: "total_increasing",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "rainWeekly",
-        &SensorConfig {
-            name: "Weekly Rain",
-            unique_id: "ambw_mqtt_weekly_rain",
-            device_class: Some("precipitation"),
-            state_topic: "homeassistant/sensor/ambientWeather/rainWeekly/state",
-            unit_of_measurement: "in",
-            state_class: "total_increasing",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "rainMonthly",
-        &SensorConfig {
-            name: "Monthly Rain",
-            unique_id: "ambw_mqtt_monthyl_rain",
-            device_class: Some("precipitation"),
-            state_topic: "homeassistant/sensor/ambientWeather/rainMonthly/state",
-            unit_of_measurement: "in",
-            state_class: "total_increasing",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "rainLifetime",
-        &SensorConfig {
-            name: "Lifetime Rain",
-            unique_id: "ambw_mqtt_lifetime_rain",
-            device_class: Some("precipitation"),
-            state_topic: "homeassistant/sensor/ambientWeather/rainLifetime/state",
-            unit_of_measurement: "in",
-            state_class: "total_increasing",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "solarRadiation",
-        &SensorConfig {
-            name: "Solar Radiation",
-            unique_id: "ambw_mqtt_solar_rad",
-            device_class: Some("irradiance"),
-            state_topic: "homeassistant/sensor/ambientWeather/solarRadiation/state",
-            unit_of_measurement: "W/m²",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "UV",
-        &SensorConfig {
-            name: "UV Index",
-            unique_id: "ambw_mqtt_uv",
-            device_class: None,
-            state_topic: "homeassistant/sensor/ambientWeather/UV/state",
-            unit_of_measurement: "Index",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "kitchenTemperature",
-        &SensorConfig {
-            name: "Kitchen Temperature",
-            unique_id: "ambw_mqtt_indoor_temp",
-            device_class: Some("temperature"),
-            state_topic: "homeassistant/sensor/ambientWeather/kitchenTemperature/state",
-            unit_of_measurement: "°F",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "kitchenHumidity",
-        &SensorConfig {
-            name: "Kitchen Humidity",
-            unique_id: "ambw_mqtt_indoor_hum",
-            device_class: Some("humidity"),
-            state_topic: "homeassistant/sensor/ambientWeather/kitchenHumidity/state",
-            unit_of_measurement: "%",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "pressure",
-        &SensorConfig {
-            name: "Outside Pressure",
-            unique_id: "ambw_mqtt_abs_press",
-            device_class: Some("atmospheric_pressure"),
-            state_topic: "homeassistant/sensor/ambientWeather/pressure/state",
-            unit_of_measurement: "hPa",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
-    publish_sensor_config(
-        &mqtt_client,
-        "relativePressure",
-        &SensorConfig {
-            name: "Outside Relative Pressure",
-            unique_id: "ambw_mqtt_rel_press",
-            device_class: Some("atmospheric_pressure"),
-            state_topic: "homeassistant/sensor/ambientWeather/relativePressure/state",
-            unit_of_measurement: "hPa",
-            state_class: "measurement",
-            device: &device,
-        },
-    );
+    DEVICE
+        .set(HashMap::from([
+            ("identifiers", "ambw_mqtt"),
+            ("manufacturer", "Ambient Weather"),
+            ("model", "WS-2902"),
+            ("name", "MQTT Weather Station"),
+            ("via_device", "ambient_weather_mqtt"),
+        ]))
+        .expect("device set exactly once");
+
+    mqtt.publish(AVAILABILITY_TOPIC, PAYLOAD_AVAILABLE, true);
+    for sensor in SENSORS {
+        publish_sensor_config(&mut mqtt, sensor);
+    }
 
-    let state = Arc::new(Mutex::new(mqtt_client));
+    let state = Arc::new(SharedState {
+        mqtt: Mutex::new(mqtt),
+        station_id: cfg.station_id.clone(),
+        station_key: cfg.station_key.clone(),
+    });
+    spawn_reconnect_task(Arc::clone(&state));
 
     let app = Router::new()
         .route("/update_weather", get(handle_weather_update))
         .with_state(state);
 
-    let addr = SocketAddr::from(([192, 168, 1, 2], 8090));
+    let addr: SocketAddr = match format!("{}:{}", cfg.listen_address, cfg.listen_port).parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            error!(%e, "unable to parse listen_address/listen_port");
+            exit(1);
+        }
+    };
     info!("Listening on {}", addr);
     if let Err(e) = axum::Server::bind(&addr)
         .serve(app.into_make_service())
@@ -341,108 +622,92 @@ async fn main() {
     clippy::missing_errors_doc
 )]
 pub async fn handle_weather_update(
-    State(mqtt_client): State<AppState>,
+    State(state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<StatusCode, StatusCode> {
     debug!(?params, "incoming payload");
 
-    if params.get("ID") != Some(&String::from("local"))
-        || params.get("PASSWORD") != Some(&String::from("key"))
+    if params.get("ID") != Some(&state.station_id)
+        || params.get("PASSWORD") != Some(&state.station_key)
     {
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let client = mqtt_client.lock().map_err(|e| {
+    spawn_windy_upload(&params);
+
+    let mut client = state.mqtt.lock().map_err(|e| {
         error!(%e, "unable to lock MQTT client mutex");
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    publish_f32(&client, &params, "tempf", "temperature", 1);
-    publish_i32(&client, &params, "humidity", "humidity");
-    publish_f32(&client, &params, "dewptf", "dewPoint", 1);
-    publish_f32(&client, &params, "windchillf", "windChill", 1);
-    publish_i32(&client, &params, "winddir", "windDir");
-    publish_f32(&client, &params, "windspeedmph", "windSpeed", 2);
-    publish_f32(&client, &params, "windgustmph", "windGust", 2);
-    publish_f32(&client, &params, "rainin", "rainHourly", 3);
-    publish_f32(&client, &params, "dailyrainin", "rainDaily", 3);
-    publish_f32(&client, &params, "weeklyrainin", "rainWeekly", 3);
-    publish_f32(&client, &params, "monthlyrainin", "rainMonthly", 3);
-    publish_f32(&client, &params, "totalrainin", "rainLifetime", 3);
-    publish_f32(&client, &params, "solarradiation", "solarRadiation", 1);
-    publish_i32(&client, &params, "UV", "UV");
-    publish_f32(&client, &params, "indoortempf", "kitchenTemperature", 1);
-    publish_i32(&client, &params, "indoorhumidity", "kitchenHumidity");
-
-    if let Some(val) = params.get("absbaromin") {
-        match val.parse::<f32>() {
-            Ok(inhg) => {
-                let payload = format!("{:.1}", inhg * 33.86); //hPa
-                debug!(topic = "pressure", payload, "publishing");
-                client.publish(
-                    "homeassistant/sensor/ambientWeather/pressure/state",
-                    &payload,
-                    false,
-                );
-            }
-            Err(e) => {
-                error!(%e, val, key = "absbaromin", "unable to parse f32 from param");
+    // Heat index is only defined for Fahrenheit inputs, so it's always computed in
+    // °F here and converted just before publishing.
+    let units = app_config().units;
+    let feels_like_f = params
+        .get("tempf")
+        .and_then(|t| t.parse::<f64>().ok())
+        .zip(params.get("humidity").and_then(|w| w.parse::<f64>().ok()))
+        .map(|(temp_f, rh)| UnitKind::Temperature.convert(heat_index_f(temp_f, rh), units));
+
+    if app_config().aggregate_state {
+        let mut payload_map = serde_json::Map::new();
+        for sensor in SENSORS {
+            match sensor.kind {
+                SensorKind::Computed => {}
+                kind => match params
+                    .get(sensor.key)
+                    .map(|val| sensor_state_value(kind, sensor.unit_kind, units, val))
+                {
+                    Some(Ok(value)) => {
+                        payload_map.insert(sensor.topic.to_string(), value);
+                    }
+                    Some(Err(e)) => {
+                        error!(%e, key = sensor.key, "unable to parse value from param");
+                    }
+                    None => error!(key = sensor.key, "missing value in params"),
+                },
             }
         }
+        if let Some(feels_like_f) = feels_like_f {
+            payload_map.insert(
+                "feelsLike".to_string(),
+                serde_json::Value::String(format!("{feels_like_f:.1}")),
+            );
+        }
+        let payload = serde_json::to_string(&payload_map).unwrap();
+        debug!(payload, "publishing aggregate state");
+        client.publish(AGGREGATE_STATE_TOPIC, &payload, false);
     } else {
-        error!(key = "absbaromin", "missing value in params");
-    }
-
-    if let Some(val) = params.get("baromin") {
-        match val.parse::<f32>() {
-            Ok(inhg) => {
-                let payload = format!("{:.1}", inhg * 33.86); //hPa
-                debug!(topic = "relativePressure", payload, "publishing");
-                client.publish(
-                    "homeassistant/sensor/ambientWeather/relativePressure/state",
-                    &payload,
-                    false,
-                );
-            }
-            Err(e) => {
-                error!(%e, val, key = "baromin", "unable to parse f32 from param");
+        for sensor in SENSORS {
+            match sensor.kind {
+                SensorKind::F32 { precision } => {
+                    publish_f32(
+                        &mut client,
+                        &params,
+                        sensor.key,
+                        sensor.topic,
+                        precision,
+                        sensor.unit_kind,
+                        units,
+                    );
+                }
+                SensorKind::F32Scaled { precision, scale } => {
+                    publish_f32_scaled(
+                        &mut client,
+                        &params,
+                        sensor.key,
+                        sensor.topic,
+                        precision,
+                        scale,
+                    );
+                }
+                SensorKind::I32 => publish_i32(&mut client, &params, sensor.key, sensor.topic),
+                SensorKind::Computed => {}
             }
         }
-    } else {
-        error!(key = "baromin", "missing value in params");
-    }
 
-    if let Some(Ok(temp_f)) = params.get("tempf").map(|t| t.parse::<f64>()) {
-        if let Some(Ok(rh)) = params.get("humidity").map(|w| w.parse::<f64>()) {
-            let heat_index_f = {
-                if temp_f < 80.0 {
-                    temp_f
-                } else {
-                    let steadman = 0.5 * (temp_f + 61.0 + (temp_f - 68.0) * 1.2 + rh * 0.094);
-                    let s_avg = (temp_f + steadman) / 2.0;
-                    if s_avg < 80.0 {
-                        steadman
-                    } else {
-                        let rothfusz = -42.379 + 2.049_015_23 * temp_f + 10.143_331_27 * rh
-                            - 0.224_755_41 * temp_f * rh
-                            - 0.006_837_83 * temp_f * temp_f
-                            - 0.054_817_17 * rh * rh
-                            + 0.001_228_74 * temp_f * temp_f * rh
-                            + 0.000_852_82 * temp_f * rh * rh
-                            - 0.000_001_99 * temp_f * temp_f * rh * rh;
-                        if rh < 13.0 && temp_f > 80.0 && temp_f < 112.0 {
-                            rothfusz
-                                - ((13.0 - rh) / 4.0)
-                                    * ((17.0 - (temp_f - 95.0).abs()) / 17.0).sqrt()
-                        } else if rh > 85.0 && temp_f > 80.0 && temp_f < 87.0 {
-                            rothfusz + ((rh - 85.0) / 10.0) * ((87.0 - temp_f) / 5.0)
-                        } else {
-                            rothfusz
-                        }
-                    }
-                }
-            };
-            let payload = format!("{heat_index_f:.1}");
+        if let Some(feels_like_f) = feels_like_f {
+            let payload = format!("{feels_like_f:.1}");
             debug!(topic = "feelsLike", payload, "publishing");
             client.publish(
                 "homeassistant/sensor/ambientWeather/feelsLike/state",
@@ -470,17 +735,102 @@ pub async fn handle_weather_update(
     Ok(StatusCode::OK)
 }
 
+fn heat_index_f(temp_f: f64, rh: f64) -> f64 {
+    if temp_f < 80.0 {
+        return temp_f;
+    }
+
+    let steadman = 0.5 * (temp_f + 61.0 + (temp_f - 68.0) * 1.2 + rh * 0.094);
+    let s_avg = (temp_f + steadman) / 2.0;
+    if s_avg < 80.0 {
+        return steadman;
+    }
+
+    let rothfusz = -42.379 + 2.049_015_23 * temp_f + 10.143_331_27 * rh
+        - 0.224_755_41 * temp_f * rh
+        - 0.006_837_83 * temp_f * temp_f
+        - 0.054_817_17 * rh * rh
+        + 0.001_228_74 * temp_f * temp_f * rh
+        + 0.000_852_82 * temp_f * rh * rh
+        - 0.000_001_99 * temp_f * temp_f * rh * rh;
+    if rh < 13.0 && temp_f > 80.0 && temp_f < 112.0 {
+        rothfusz - ((13.0 - rh) / 4.0) * ((17.0 - (temp_f - 95.0).abs()) / 17.0).sqrt()
+    } else if rh > 85.0 && temp_f > 80.0 && temp_f < 87.0 {
+        rothfusz + ((rh - 85.0) / 10.0) * ((87.0 - temp_f) / 5.0)
+    } else {
+        rothfusz
+    }
+}
+
+/// Parse a sensor's raw param value per its `SensorKind`, formatting floats as
+/// fixed-precision strings so `{:.precision$}`'s trailing-zero/decimal-count
+/// guarantee survives JSON round-tripping (a bare JSON number like `21.50` does not).
+fn sensor_state_value(
+    kind: SensorKind,
+    unit_kind: UnitKind,
+    units: Units,
+    raw: &str,
+) -> Result<serde_json::Value, String> {
+    match kind {
+        SensorKind::F32 { precision } => raw
+            .parse::<f64>()
+            .map(|v| {
+                serde_json::Value::String(format!("{:.precision$}", unit_kind.convert(v, units)))
+            })
+            .map_err(|e| e.to_string()),
+        SensorKind::F32Scaled { precision, scale } => raw
+            .parse::<f64>()
+            .map(|v| serde_json::Value::String(format!("{:.precision$}", v * f64::from(scale))))
+            .map_err(|e| e.to_string()),
+        SensorKind::I32 => raw
+            .parse::<i32>()
+            .map(serde_json::Value::from)
+            .map_err(|e| e.to_string()),
+        SensorKind::Computed => unreachable!("computed sensors aren't published from the registry"),
+    }
+}
+
 fn publish_f32(
-    client: &MqttClient,
+    client: &mut Mqtt,
     params: &HashMap<String, String>,
     key: &str,
     topic: &str,
     precision: usize,
+    unit_kind: UnitKind,
+    units: Units,
+) {
+    if let Some(val) = params.get(key) {
+        match val.parse::<f64>() {
+            Ok(parsed) => {
+                let payload = format!("{:.precision$}", unit_kind.convert(parsed, units));
+                debug!(topic, payload, "publishing");
+                client.publish(
+                    &format!("homeassistant/sensor/ambientWeather/{topic}/state"),
+                    &payload,
+                    false,
+                );
+            }
+            Err(e) => {
+                error!(%e, val, key, "unable to parse f32 from param");
+            }
+        }
+    } else {
+        error!(key, "missing value in params");
+    }
+}
+
+fn publish_f32_scaled(
+    client: &mut Mqtt,
+    params: &HashMap<String, String>,
+    key: &str,
+    topic: &str,
+    precision: usize,
+    scale: f32,
 ) {
     if let Some(val) = params.get(key) {
         match val.parse::<f32>() {
             Ok(parsed) => {
-                let payload = format!("{parsed:.precision$}");
+                let payload = format!("{:.precision$}", parsed * scale);
                 debug!(topic, payload, "publishing");
                 client.publish(
                     &format!("homeassistant/sensor/ambientWeather/{topic}/state"),
@@ -497,7 +847,7 @@ fn publish_f32(
     }
 }
 
-fn publish_i32(client: &MqttClient, params: &HashMap<String, String>, key: &str, topic: &str) {
+fn publish_i32(client: &mut Mqtt, params: &HashMap<String, String>, key: &str, topic: &str) {
     if let Some(val) = params.get(key) {
         match val.parse::<i32>() {
             Ok(parsed) => {
@@ -518,11 +868,38 @@ fn publish_i32(client: &MqttClient, params: &HashMap<String, String>, key: &str,
     }
 }
 
-fn publish_sensor_config(client: &MqttClient, topic: &str, config: &SensorConfig) {
-    let payload = serde_json::to_string(config).unwrap();
-    debug!(topic, payload, "publishing config");
+fn publish_sensor_config(client: &mut Mqtt, sensor: &SensorDef) {
+    let (state_topic, value_template) = if app_config().aggregate_state {
+        (
+            AGGREGATE_STATE_TOPIC.to_string(),
+            Some(format!("{{{{ value_json.{} }}}}", sensor.topic)),
+        )
+    } else {
+        (
+            format!("homeassistant/sensor/ambientWeather/{}/state", sensor.topic),
+            None,
+        )
+    };
+
+    let config = SensorConfig {
+        name: sensor.name,
+        unique_id: sensor.unique_id,
+        device_class: sensor.device_class,
+        device: device(),
+        state_topic,
+        value_template,
+        unit_of_measurement: sensor
+            .unit_kind
+            .unit_of_measurement(app_config().units, sensor.unit_of_measurement),
+        state_class: sensor.state_class,
+        availability_topic: AVAILABILITY_TOPIC,
+        payload_available: PAYLOAD_AVAILABLE,
+        payload_not_available: PAYLOAD_NOT_AVAILABLE,
+    };
+    let payload = serde_json::to_string(&config).unwrap();
+    debug!(topic = sensor.topic, payload, "publishing config");
     client.publish(
-        &format!("homeassistant/sensor/ambientWeather/{topic}/config"),
+        &format!("homeassistant/sensor/ambientWeather/{}/config", sensor.topic),
         &payload,
         true,
     );